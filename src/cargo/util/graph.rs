@@ -2,6 +2,7 @@ use std::fmt;
 use std::hash::Hash;
 use std::collections::hash_set::{HashSet, Iter};
 use std::collections::hash_map::{HashMap, Keys};
+use std::collections::VecDeque;
 
 pub struct Graph<N> {
     nodes: HashMap<N, HashSet<N>>,
@@ -44,30 +45,71 @@ impl<N: Eq + Hash + Clone> Graph<N> {
         self.nodes.get(node).map(|set| set.iter())
     }
 
-    pub fn sort(&self) -> Option<Vec<N>> {
+    pub fn sort(&self) -> Result<Vec<N>, Vec<N>> {
         let mut ret = Vec::new();
         let mut marks = HashMap::new();
 
         for node in self.nodes.keys() {
-            self.visit(node, &mut ret, &mut marks);
+            if marks.contains_key(node) {
+                continue;
+            }
+            if let Some(cycle) = self.visit(node, &mut ret, &mut marks) {
+                return Err(cycle);
+            }
         }
 
-        Some(ret)
+        Ok(ret)
     }
 
-    fn visit(&self, node: &N, dst: &mut Vec<N>, marks: &mut HashMap<N, Mark>) {
-        if marks.contains_key(node) {
-            return;
-        }
+    /// Appends `start` and its transitive children to `dst` in post-order,
+    /// or returns `Some(cycle)` the first time a back edge is found.
+    fn visit(&self, start: &N, dst: &mut Vec<N>, marks: &mut HashMap<N, Mark>) -> Option<Vec<N>> {
+        let mut stack: Vec<(N, Vec<N>, usize)> = Vec::new();
+        marks.insert(start.clone(), Mark::InProgress);
+        stack.push((start.clone(), self.children_of(start), 0));
 
-        marks.insert(node.clone(), Mark::InProgress);
+        while !stack.is_empty() {
+            let next_child = {
+                let &mut (_, ref children, ref mut idx) = stack.last_mut().unwrap();
+                let next_child = children.get(*idx).cloned();
+                if next_child.is_some() {
+                    *idx += 1;
+                }
+                next_child
+            };
 
-        for child in &self.nodes[node] {
-            self.visit(child, dst, marks);
+            match next_child {
+                Some(child) => {
+                    match marks.get(&child) {
+                        Some(&Mark::Done) => continue,
+                        Some(&Mark::InProgress) => {
+                            let pos = stack
+                                .iter()
+                                .position(|&(ref n, _, _)| *n == child)
+                                .expect("node marked in-progress must be on the stack");
+                            let mut cycle: Vec<N> = stack[pos..]
+                                .iter()
+                                .map(|&(ref n, _, _)| n.clone())
+                                .collect();
+                            cycle.push(child);
+                            return Some(cycle);
+                        }
+                        None => {}
+                    }
+
+                    marks.insert(child.clone(), Mark::InProgress);
+                    let grandchildren = self.children_of(&child);
+                    stack.push((child, grandchildren, 0));
+                }
+                None => {
+                    let (node, _, _) = stack.pop().unwrap();
+                    dst.push(node.clone());
+                    marks.insert(node, Mark::Done);
+                }
+            }
         }
 
-        dst.push(node.clone());
-        marks.insert(node.clone(), Mark::Done);
+        None
     }
 
     pub fn iter(&self) -> Nodes<N> {
@@ -97,6 +139,347 @@ impl<N: Eq + Hash + Clone> Graph<N> {
         }
         result
     }
+
+    /// Returns every node transitively reachable from `node`.
+    pub fn reachable_from(&self, node: &N) -> HashSet<N> {
+        let mut seen = HashSet::new();
+        let mut stack = self.nodes
+            .get(node)
+            .map(|children| children.iter().cloned().collect())
+            .unwrap_or_else(Vec::new);
+
+        while let Some(n) = stack.pop() {
+            if !seen.insert(n.clone()) {
+                continue;
+            }
+            if let Some(children) = self.nodes.get(&n) {
+                stack.extend(children.iter().cloned());
+            }
+        }
+
+        seen
+    }
+
+    /// Returns every node that transitively depends on `node` (`reachable_from`
+    /// over the reversed graph).
+    pub fn transitive_dependents(&self, node: &N) -> HashSet<N> {
+        let reverse = self.reverse_edges();
+        let mut seen = HashSet::new();
+        let mut stack = reverse
+            .get(node)
+            .map(|parents| parents.iter().cloned().collect())
+            .unwrap_or_else(Vec::new);
+
+        while let Some(n) = stack.pop() {
+            if !seen.insert(n.clone()) {
+                continue;
+            }
+            if let Some(parents) = reverse.get(&n) {
+                stack.extend(parents.iter().cloned());
+            }
+        }
+
+        seen
+    }
+
+    /// Builds the reverse adjacency map (child -> set of parents) once, so
+    /// that dependents-style queries are linear in the number of edges
+    /// rather than re-scanning every node's edge set for each query.
+    fn reverse_edges(&self) -> HashMap<N, HashSet<N>> {
+        let mut reverse: HashMap<N, HashSet<N>> = HashMap::new();
+        for (parent, children) in &self.nodes {
+            for child in children {
+                reverse
+                    .entry(child.clone())
+                    .or_insert_with(HashSet::new)
+                    .insert(parent.clone());
+            }
+        }
+        reverse
+    }
+
+    /// Returns the transpose of this graph: every edge `a -> b` becomes `b -> a`.
+    pub fn reversed(&self) -> Graph<N> {
+        let mut reversed = Graph::new();
+        for node in self.nodes.keys() {
+            reversed.add(node.clone(), &[]);
+        }
+        for (parent, children) in &self.nodes {
+            for child in children {
+                reversed.link(child.clone(), parent.clone());
+            }
+        }
+        reversed
+    }
+
+    /// Nodes with no incoming edges.
+    pub fn roots(&self) -> Vec<&N> {
+        let reverse = self.reverse_edges();
+        self.nodes
+            .keys()
+            .filter(|n| reverse.get(*n).map_or(true, |parents| parents.is_empty()))
+            .collect()
+    }
+
+    /// Nodes with no outgoing edges.
+    pub fn leaves(&self) -> Vec<&N> {
+        self.nodes.keys().filter(|n| self.nodes[*n].is_empty()).collect()
+    }
+
+    /// Like `roots`, but relative to `subset`: parents outside `subset` don't count.
+    pub fn relative_roots<'a>(&'a self, subset: &HashSet<N>) -> Vec<&'a N> {
+        let reverse = self.reverse_edges();
+        self.nodes
+            .keys()
+            .filter(|n| subset.contains(n))
+            .filter(|n| {
+                reverse
+                    .get(*n)
+                    .map_or(true, |parents| !parents.iter().any(|p| subset.contains(p)))
+            })
+            .collect()
+    }
+
+    /// Like `leaves`, but relative to `subset`: children outside `subset` don't count.
+    pub fn relative_heads<'a>(&'a self, subset: &HashSet<N>) -> Vec<&'a N> {
+        self.nodes
+            .keys()
+            .filter(|n| subset.contains(n))
+            .filter(|n| !self.nodes[*n].iter().any(|c| subset.contains(c)))
+            .collect()
+    }
+
+    /// Returns every distinct simple cycle in the graph, deduped regardless
+    /// of which node it was discovered from.
+    pub fn cycles(&self) -> Vec<Vec<N>>
+    where
+        N: Ord,
+    {
+        let mut cycles = Vec::new();
+        let mut seen = HashSet::new();
+
+        for start in self.nodes.keys() {
+            self.find_cycles(start, &mut seen, &mut cycles);
+        }
+
+        cycles
+    }
+
+    /// Explicit-stack DFS from `start`, same frame-with-child-index pattern
+    /// as `visit`/`postorder_from`, so a deep dependency chain can't overflow
+    /// the native stack the way a plain recursive walk would.
+    fn find_cycles(&self, start: &N, seen: &mut HashSet<Vec<N>>, cycles: &mut Vec<Vec<N>>)
+    where
+        N: Ord,
+    {
+        let mut on_path = HashSet::new();
+        let mut stack: Vec<(N, Vec<N>, usize)> = Vec::new();
+
+        on_path.insert(start.clone());
+        stack.push((start.clone(), self.children_of(start), 0));
+
+        while !stack.is_empty() {
+            let next_child = {
+                let &mut (_, ref children, ref mut idx) = stack.last_mut().unwrap();
+                let next = children.get(*idx).cloned();
+                if next.is_some() {
+                    *idx += 1;
+                }
+                next
+            };
+
+            match next_child {
+                Some(child) => {
+                    if on_path.contains(&child) {
+                        let pos = stack
+                            .iter()
+                            .position(|&(ref n, _, _)| *n == child)
+                            .unwrap();
+                        let cycle: Vec<N> =
+                            stack[pos..].iter().map(|&(ref n, _, _)| n.clone()).collect();
+                        let cycle = Graph::canonicalize_cycle(&cycle);
+                        if seen.insert(cycle.clone()) {
+                            cycles.push(cycle);
+                        }
+                    } else {
+                        on_path.insert(child.clone());
+                        let grandchildren = self.children_of(&child);
+                        stack.push((child, grandchildren, 0));
+                    }
+                }
+                None => {
+                    let (node, _, _) = stack.pop().unwrap();
+                    on_path.remove(&node);
+                }
+            }
+        }
+    }
+
+    /// Rotates a cycle so its minimum node is first, giving the same cycle a
+    /// single canonical representation no matter which node it was
+    /// discovered from.
+    fn canonicalize_cycle(cycle: &[N]) -> Vec<N>
+    where
+        N: Ord,
+    {
+        let min_pos = cycle
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, n)| n)
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        cycle[min_pos..]
+            .iter()
+            .chain(cycle[..min_pos].iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Computes the immediate dominator of every node reachable from `root`
+    /// (iterative Cooper-Harvey-Kennedy); `root` itself is excluded.
+    pub fn dominators(&self, root: &N) -> HashMap<N, N> {
+        let postorder = self.postorder_from(root);
+        let index: HashMap<N, usize> = postorder
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.clone(), i))
+            .collect();
+        let rpo: Vec<N> = postorder.into_iter().rev().collect();
+        let reverse = self.reverse_edges();
+
+        let mut idom: HashMap<N, N> = HashMap::new();
+        idom.insert(root.clone(), root.clone());
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for node in rpo.iter().skip(1) {
+                let mut new_idom: Option<N> = None;
+
+                if let Some(preds) = reverse.get(node) {
+                    for pred in preds {
+                        if !idom.contains_key(pred) {
+                            continue;
+                        }
+                        new_idom = Some(match new_idom {
+                            None => pred.clone(),
+                            Some(cur) => Graph::intersect(&cur, pred, &idom, &index),
+                        });
+                    }
+                }
+
+                if let Some(new_idom) = new_idom {
+                    if idom.get(node) != Some(&new_idom) {
+                        idom.insert(node.clone(), new_idom);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        idom.remove(root);
+        idom
+    }
+
+    /// Walks two nodes up the (partially built) idom tree, using their
+    /// postorder numbers, until they meet at their common dominator.
+    fn intersect(a: &N, b: &N, idom: &HashMap<N, N>, index: &HashMap<N, usize>) -> N {
+        let mut a = a.clone();
+        let mut b = b.clone();
+
+        while a != b {
+            while index[&a] < index[&b] {
+                a = idom[&a].clone();
+            }
+            while index[&b] < index[&a] {
+                b = idom[&b].clone();
+            }
+        }
+
+        a
+    }
+
+    /// Postorder DFS over the nodes reachable from `root`, following forward
+    /// (dependency) edges. Iterative to keep stack usage bounded on deep
+    /// dependency chains.
+    fn postorder_from(&self, root: &N) -> Vec<N> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        let mut stack: Vec<(N, Vec<N>, usize)> = Vec::new();
+
+        visited.insert(root.clone());
+        stack.push((root.clone(), self.children_of(root), 0));
+
+        while !stack.is_empty() {
+            let next_child = {
+                let &mut (_, ref children, ref mut idx) = stack.last_mut().unwrap();
+                let next = children.get(*idx).cloned();
+                if next.is_some() {
+                    *idx += 1;
+                }
+                next
+            };
+
+            match next_child {
+                Some(child) => {
+                    if visited.insert(child.clone()) {
+                        let grandchildren = self.children_of(&child);
+                        stack.push((child, grandchildren, 0));
+                    }
+                }
+                None => {
+                    let (node, _, _) = stack.pop().unwrap();
+                    order.push(node);
+                }
+            }
+        }
+
+        order
+    }
+
+    fn children_of(&self, node: &N) -> Vec<N> {
+        self.nodes
+            .get(node)
+            .map(|children| children.iter().cloned().collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Returns the fewest-edge path from `from` to `to`, or `None` if unreachable.
+    pub fn shortest_path(&self, from: &N, to: &N) -> Option<Vec<N>> {
+        if from == to {
+            return Some(vec![from.clone()]);
+        }
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut predecessors: HashMap<N, N> = HashMap::new();
+
+        visited.insert(from.clone());
+        queue.push_back(from.clone());
+
+        while let Some(node) = queue.pop_front() {
+            for child in self.children_of(&node) {
+                if !visited.insert(child.clone()) {
+                    continue;
+                }
+                predecessors.insert(child.clone(), node.clone());
+
+                if child == *to {
+                    let mut path = vec![child];
+                    while let Some(pred) = predecessors.get(path.last().unwrap()) {
+                        path.push(pred.clone());
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+
+                queue.push_back(child);
+            }
+        }
+
+        None
+    }
 }
 
 impl<N: Eq + Hash + Clone> Default for Graph<N> {
@@ -137,3 +520,245 @@ impl<N: Eq + Hash + Clone> Clone for Graph<N> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Graph;
+    use std::collections::HashSet;
+
+    #[test]
+    fn sort_does_not_panic_on_a_node_only_ever_seen_as_a_child() {
+        let mut g: Graph<i32> = Graph::new();
+        g.link(1, 2);
+
+        assert_eq!(g.sort(), Ok(vec![2, 1]));
+    }
+
+    #[test]
+    fn sort_orders_each_node_after_its_dependencies() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1, 2]);
+        g.add(1, &[2]);
+        g.add(2, &[]);
+
+        let order = g.sort().unwrap();
+        let pos = |n: i32| order.iter().position(|&x| x == n).unwrap();
+
+        assert!(pos(2) < pos(1));
+        assert!(pos(1) < pos(0));
+    }
+
+    #[test]
+    fn sort_reports_a_cycle_instead_of_a_bogus_order() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[2]);
+        g.add(2, &[0]);
+
+        let cycle = g.sort().unwrap_err();
+        assert_eq!(cycle.first(), cycle.last());
+        assert!(cycle.contains(&0));
+        assert!(cycle.contains(&1));
+        assert!(cycle.contains(&2));
+    }
+
+    #[test]
+    fn cycles_dedups_overlapping_cycles_found_from_different_entry_points() {
+        let mut g: Graph<i32> = Graph::new();
+        // 0 -> 1 -> 2 -> 0 (one cycle) and 1 -> 3 -> 1 (another, sharing node 1).
+        g.add(0, &[1]);
+        g.add(1, &[2, 3]);
+        g.add(2, &[0]);
+        g.add(3, &[1]);
+
+        let mut cycles = g.cycles();
+        cycles.sort();
+
+        assert_eq!(cycles, vec![vec![0, 1, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    fn cycles_on_acyclic_graph_is_empty() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[2]);
+        g.add(2, &[]);
+
+        assert!(g.cycles().is_empty());
+    }
+
+    #[test]
+    fn dominators_on_chain() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[2]);
+        g.add(2, &[3]);
+        g.add(3, &[]);
+
+        let idom = g.dominators(&0);
+        assert_eq!(idom.get(&1), Some(&0));
+        assert_eq!(idom.get(&2), Some(&1));
+        assert_eq!(idom.get(&3), Some(&2));
+        assert_eq!(idom.len(), 3);
+    }
+
+    #[test]
+    fn dominators_on_diamond_merge_point_is_dominated_by_root() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1, 2]);
+        g.add(1, &[3]);
+        g.add(2, &[3]);
+        g.add(3, &[]);
+
+        let idom = g.dominators(&0);
+        assert_eq!(idom.get(&1), Some(&0));
+        assert_eq!(idom.get(&2), Some(&0));
+        assert_eq!(idom.get(&3), Some(&0));
+    }
+
+    #[test]
+    fn dominators_excludes_root_and_unreachable_nodes() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[0]); // cycle back to root
+        g.add(99, &[100]); // unreachable from 0
+        g.add(100, &[]);
+
+        let idom = g.dominators(&0);
+        assert_eq!(idom.get(&1), Some(&0));
+        assert!(!idom.contains_key(&0));
+        assert!(!idom.contains_key(&99));
+        assert!(!idom.contains_key(&100));
+    }
+
+    #[test]
+    fn shortest_path_picks_the_fewest_edge_route() {
+        // 0 -> 1 -> 2 -> 3 is three edges; the direct 0 -> 3 edge is shorter
+        // and must win regardless of HashSet child iteration order.
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1, 3]);
+        g.add(1, &[2]);
+        g.add(2, &[3]);
+
+        assert_eq!(g.shortest_path(&0, &3), Some(vec![0, 3]));
+    }
+
+    #[test]
+    fn shortest_path_from_a_node_to_itself_is_a_single_element_path() {
+        let g: Graph<i32> = Graph::new();
+
+        assert_eq!(g.shortest_path(&0, &0), Some(vec![0]));
+    }
+
+    #[test]
+    fn shortest_path_returns_none_when_unreachable() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(2, &[3]);
+
+        assert_eq!(g.shortest_path(&0, &3), None);
+    }
+
+    #[test]
+    fn reachable_from_collects_all_transitive_dependencies() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1, 3]);
+        g.add(1, &[2]);
+        g.add(2, &[]);
+        g.add(3, &[]);
+
+        assert_eq!(
+            g.reachable_from(&0),
+            vec![1, 2, 3].into_iter().collect::<HashSet<_>>()
+        );
+        assert!(g.reachable_from(&2).is_empty());
+    }
+
+    #[test]
+    fn reachable_from_includes_the_start_node_when_it_sits_on_a_cycle() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[0]);
+
+        assert_eq!(
+            g.reachable_from(&0),
+            vec![0, 1].into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test]
+    fn transitive_dependents_collects_everything_that_would_need_rebuilding() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[2]);
+        g.add(3, &[2]);
+
+        assert_eq!(
+            g.transitive_dependents(&2),
+            vec![0, 1, 3].into_iter().collect::<HashSet<_>>()
+        );
+        assert!(g.transitive_dependents(&0).is_empty());
+    }
+
+    #[test]
+    fn reversed_flips_every_edge() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1]);
+        g.add(1, &[2]);
+
+        let reversed = g.reversed();
+        assert_eq!(
+            reversed.edges(&2).unwrap().collect::<HashSet<_>>(),
+            vec![&1].into_iter().collect()
+        );
+        assert_eq!(
+            reversed.edges(&1).unwrap().collect::<HashSet<_>>(),
+            vec![&0].into_iter().collect()
+        );
+        assert_eq!(reversed.edges(&0).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn roots_and_leaves_of_a_diamond() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1, 2]);
+        g.add(1, &[3]);
+        g.add(2, &[3]);
+        g.add(3, &[]);
+
+        assert_eq!(
+            g.roots().into_iter().cloned().collect::<HashSet<_>>(),
+            vec![0].into_iter().collect()
+        );
+        assert_eq!(
+            g.leaves().into_iter().cloned().collect::<HashSet<_>>(),
+            vec![3].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn relative_roots_and_heads_ignore_edges_outside_the_subset() {
+        let mut g: Graph<i32> = Graph::new();
+        g.add(0, &[1, 2]);
+        g.add(1, &[3]);
+        g.add(2, &[3]);
+        g.add(3, &[]);
+
+        let subset: HashSet<i32> = vec![1, 2, 3].into_iter().collect();
+
+        assert_eq!(
+            g.relative_roots(&subset)
+                .into_iter()
+                .cloned()
+                .collect::<HashSet<_>>(),
+            vec![1, 2].into_iter().collect()
+        );
+        assert_eq!(
+            g.relative_heads(&subset)
+                .into_iter()
+                .cloned()
+                .collect::<HashSet<_>>(),
+            vec![3].into_iter().collect()
+        );
+    }
+}